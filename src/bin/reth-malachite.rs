@@ -53,6 +53,16 @@ fn main() -> eyre::Result<()> {
                 "reth-malachite-1".to_string(),
                 "node-0".to_string(),
                 "127.0.0.1:26657".parse()?,
+            )
+            // Retain a verifiable finality justification every 512 heights;
+            // intermediate commit certificates are pruned once their
+            // boundary is justified.
+            .with_justification_period(reth_malachite::consensus::handler::DEFAULT_JUSTIFICATION_PERIOD)
+            // Erasure-code every proposal into 4 data shards + 2 parity
+            // shards, so it survives losing up to 2 of the 6 shards.
+            .with_redundancy_ratio(
+                reth_malachite::consensus::handler::DEFAULT_DATA_SHARDS,
+                reth_malachite::consensus::handler::DEFAULT_PARITY_SHARDS,
             );
 
             // Create the Malachite consensus node
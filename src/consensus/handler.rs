@@ -1,10 +1,37 @@
 //! Consensus message handler that bridges Malachite consensus to the Reth application
 
+mod erasure;
+mod justification;
+mod mempool;
+mod proposal_budget;
+mod vote_extension;
+
 use crate::{app::State, context::MalachiteContext};
+pub use erasure::{ShardHeader, ShardReassembly};
 use eyre::eyre;
+pub use justification::{Justification, JustificationStore};
 use malachitebft_app_channel::{AppMsg, Channels, ConsensusMsg, NetworkMsg};
-use malachitebft_core_types::{Height as _, Round, Validity};
-use tracing::{error, info};
+use malachitebft_core_types::{Height as _, Round, Validity, Value as _};
+pub use mempool::{Batch, BatchCertificate, BatchDigest, Mempool};
+use proposal_budget::gas_target_for_round;
+use tracing::{error, info, warn};
+use vote_extension::VoteExtension;
+
+/// Default number of decided heights between two retained finality
+/// justifications, used when `EngineConfig::justification_period` is unset.
+pub const DEFAULT_JUSTIFICATION_PERIOD: u64 = 512;
+
+/// Rough gas cost of a single batch, used to size how many batch digests fit
+/// under a round's gas target.
+const GAS_PER_BATCH: u64 = 1_000_000;
+
+/// Default erasure-coding redundancy ratio for proposal parts, used when
+/// `EngineConfig::redundancy_ratio` is unset: every value is split into
+/// `DEFAULT_DATA_SHARDS` data shards plus `DEFAULT_PARITY_SHARDS` parity
+/// shards, so it can be reconstructed from any `DEFAULT_DATA_SHARDS` of the
+/// `DEFAULT_DATA_SHARDS + DEFAULT_PARITY_SHARDS` shards that arrive.
+pub const DEFAULT_DATA_SHARDS: usize = 4;
+pub const DEFAULT_PARITY_SHARDS: usize = 2;
 
 /// Run the consensus message handler loop
 ///
@@ -13,7 +40,17 @@ use tracing::{error, info};
 pub async fn run_consensus_handler(
     state: &State,
     channels: &mut Channels<MalachiteContext>,
+    justification_period: u64,
+    data_shards: usize,
+    parity_shards: usize,
 ) -> eyre::Result<()> {
+    // Repopulate period-boundary justifications from `state`'s own
+    // persisted decided-value history, so a restart doesn't lose every
+    // justification this node already produced.
+    let mut justifications = JustificationStore::rebuild_from_state(justification_period, state).await?;
+    let mut mempool = Mempool::new();
+    let mut shard_reassembly = ShardReassembly::new();
+
     while let Some(msg) = channels.consensus.recv().await {
         match msg {
             // Consensus is ready to start
@@ -51,8 +88,24 @@ pub async fn run_consensus_handler(
                 };
                 state.set_current_role(app_role)?;
 
-                // Check if we have any pending proposals for this height/round
-                let proposals = vec![]; // TODO: Query from state storage
+                // Re-propose anything we are locked on: every value this node
+                // previously built or received (via `received_proposal_part`
+                // / `store_synced_proposal`) at this height whose round is
+                // `<=` the new round. Each stored proposal carries its
+                // original `valid_round`, proposer and validity, so the
+                // consensus engine can safely re-propose a locked value
+                // without rebuilding from the mempool, even after a restart.
+                //
+                // The storage-backed lookup itself - `State::get_pending_proposals`
+                // - lives on `State` in `app.rs`, which is not part of this
+                // source tree, so it isn't implemented here; this arm only
+                // calls it.
+                let proposals = state.get_pending_proposals(height, round).await?;
+
+                // A new height means any shard buffers left over from a
+                // skipped or abandoned round at an earlier height can never
+                // complete; drop them instead of holding them forever.
+                shard_reassembly.prune_below(height.as_u64());
 
                 if reply_value.send(proposals).is_err() {
                     error!("Failed to send StartedRound reply");
@@ -63,7 +116,7 @@ pub async fn run_consensus_handler(
             AppMsg::GetValue {
                 height,
                 round,
-                timeout: _,
+                timeout,
                 reply,
             } => {
                 info!(%height, %round, "Consensus requesting value to propose");
@@ -77,15 +130,72 @@ pub async fn run_consensus_handler(
                         }
                     }
                     None => {
-                        // Build a new value
-                        match state.propose_value(height, round).await {
+                        // Shrink the target block size as rounds increase, so
+                        // a slow payload build in a later round is cheaper to
+                        // redo and more likely to finish within `timeout`.
+                        let gas_target = gas_target_for_round(round);
+
+                        // Only propose batches we already have an
+                        // availability certificate *and* the data for, so
+                        // the proposal is just an ordered list of digests -
+                        // the transactions themselves already rode the
+                        // mempool's own gossip path, off the critical
+                        // consensus path. There is no gossip topic in this
+                        // tree yet, so the mempool only ever holds batches
+                        // this node self-certified below; until real gossip
+                        // lands, fall back to a full-value build rather than
+                        // silently propose an empty block when it's empty.
+                        let digests =
+                            mempool.select_available_digests(gas_target, GAS_PER_BATCH);
+
+                        // Build a new value, but never let the build run past
+                        // the timeout the consensus engine gave us for this
+                        // round - a stalled build must not stall the round.
+                        let proposal = match tokio::time::timeout(timeout, async {
+                            if digests.is_empty() {
+                                let proposal =
+                                    state.propose_value_with_gas_target(height, round, gas_target).await?;
+                                let digest = alloy_primitives::keccak256(
+                                    crate::app::encode_value(&proposal.value),
+                                )
+                                .0;
+                                let batch = Batch {
+                                    digest,
+                                    transactions: vec![crate::app::encode_value(&proposal.value)],
+                                };
+                                mempool.self_certify(batch);
+                                mempool.mark_proposed(height.as_u64(), round.as_i64(), vec![digest]);
+                                Ok(proposal)
+                            } else {
+                                mempool.mark_proposed(height.as_u64(), round.as_i64(), digests.clone());
+                                state.propose_value_from_digests(height, round, digests).await
+                            }
+                        })
+                        .await
+                        {
+                            Ok(result) => result,
+                            Err(_) => {
+                                error!(%height, %round, ?timeout, "Proposal build exceeded GetValue timeout, falling back to an empty block");
+                                state.propose_empty_value(height, round).await
+                            }
+                        };
+
+                        match proposal {
                             Ok(proposal) => {
                                 if reply.send(proposal.clone()).is_err() {
                                     error!("Failed to send GetValue reply");
                                 }
 
-                                // Stream the proposal parts to peers
-                                for part in state.stream_proposal(proposal, Round::Nil) {
+                                // Stream the proposal as erasure-coded parts
+                                // so a peer missing a few of them can still
+                                // reconstruct the value instead of waiting
+                                // on a full restream.
+                                for part in state.stream_proposal(
+                                    proposal,
+                                    Round::Nil,
+                                    data_shards,
+                                    parity_shards,
+                                ) {
                                     channels
                                         .network
                                         .send(NetworkMsg::PublishProposalPart(part))
@@ -101,24 +211,86 @@ pub async fn run_consensus_handler(
                 }
             }
 
-            // Vote extension handling (not used for now)
-            AppMsg::ExtendVote { reply, .. } => {
-                if reply.send(None).is_err() {
+            // Consensus is about to precommit `value_id`: attest to the
+            // execution outcome so that `Decided` can tell whether every
+            // validator who voted actually agreed on the resulting state.
+            AppMsg::ExtendVote {
+                height,
+                round,
+                value_id,
+                reply,
+            } => {
+                let extension = match state.execute_for_vote_extension(height, round, value_id).await {
+                    Ok(attestation) => Some(attestation.encode().into()),
+                    Err(e) => {
+                        error!(%e, %height, %round, "Failed to execute block for vote extension");
+                        None
+                    }
+                };
+
+                if reply.send(extension).is_err() {
                     error!("Failed to send ExtendVote reply");
                 }
             }
 
-            AppMsg::VerifyVoteExtension { reply, .. } => {
-                if reply.send(Ok(())).is_err() {
+            // A peer's precommit carries a vote extension: re-derive the
+            // execution outcome locally and reject the extension if it
+            // disagrees, so a diverging proposer/validator can't sneak a
+            // bad state root into the commit certificate.
+            AppMsg::VerifyVoteExtension {
+                height,
+                round,
+                value_id,
+                extension,
+                reply,
+            } => {
+                let outcome = match VoteExtension::decode(extension.as_ref()) {
+                    Some(extension) => match state
+                        .execute_for_vote_extension(height, round, value_id)
+                        .await
+                    {
+                        Ok(expected) if expected == extension => Ok(()),
+                        Ok(expected) => {
+                            warn!(
+                                %height, %round,
+                                expected_state_root = %expected.post_state_root,
+                                got_state_root = %extension.post_state_root,
+                                "Vote extension disagrees with local execution (possible equivocation)"
+                            );
+                            Err(eyre!("vote extension does not match locally derived execution result"))
+                        }
+                        Err(e) => {
+                            error!(%e, %height, %round, "Failed to re-derive execution result for vote extension");
+                            Err(eyre!("failed to re-derive execution result: {e}"))
+                        }
+                    },
+                    None => {
+                        warn!(%height, %round, "Received malformed vote extension");
+                        Err(eyre!("malformed vote extension"))
+                    }
+                };
+
+                if reply.send(outcome).is_err() {
                     error!("Failed to send VerifyVoteExtension reply");
                 }
             }
 
-            // Received a proposal part from another validator
+            // Received a proposal part from another validator. Proposal
+            // parts now carry batch digests rather than transaction bytes,
+            // so this resolves each digest against the local mempool instead
+            // of transferring payload again; only a digest genuinely missing
+            // from the mempool blocks consensus, and only that batch needs a
+            // targeted fetch rather than a full restream. Parts also carry
+            // one erasure-coded shard each: `shard_reassembly` buffers them
+            // per (height, round, value_id) and the value completes as soon
+            // as any `k` of the `k + m` shards have arrived.
             AppMsg::ReceivedProposalPart { from, part, reply } => {
                 info!(%from, "Received proposal part");
 
-                match state.received_proposal_part(from, part).await {
+                match state
+                    .received_proposal_part(from, part, &mempool, &mut shard_reassembly)
+                    .await
+                {
                     Ok(proposed_value) => {
                         if reply.send(proposed_value).is_err() {
                             error!("Failed to send ReceivedProposalPart reply");
@@ -157,6 +329,30 @@ pub async fn run_consensus_handler(
                 // Commit the decided value
                 match state.commit(certificate.clone(), extensions).await {
                     Ok(_) => {
+                        // Record the certificate in memory and, at every
+                        // `justification_period` boundary, turn it into a
+                        // compact justification light clients can verify
+                        // without replaying every block.
+                        justifications.record_decided(
+                            certificate.height,
+                            certificate.clone(),
+                            state.get_validator_set(certificate.height),
+                        );
+
+                        // Stop re-proposing whatever this node self-certified
+                        // into the mempool for this height - it has already
+                        // been included in a decided value, so selecting it
+                        // again at a later height would silently re-propose
+                        // stale contents forever.
+                        mempool.evict_decided(certificate.height.as_u64(), certificate.round.as_i64());
+                        if let Some(justification) = justifications.get_justification(certificate.height) {
+                            info!(
+                                height = %certificate.height,
+                                justified_height = %justification.height,
+                                "Height covered by a retained finality justification"
+                            );
+                        }
+
                         // Move to next height
                         let current = state.current_height()?;
                         let next_height = current.increment();
@@ -222,11 +418,41 @@ pub async fn run_consensus_handler(
                 }
             }
 
-            // Request for a decided value at a specific height
+            // Request for a decided value at a specific height.
             AppMsg::GetDecidedValue { height, reply } => {
                 info!(%height, "Request for decided value");
 
                 let decided_value = state.get_decided_value(height).await;
+                if decided_value.is_none() {
+                    // The raw value itself may have been pruned, but if
+                    // `height` falls within an already-justified period its
+                    // finality can still be proven from the retained
+                    // justification (no replay needed). This handler has no
+                    // `GetJustification` wire path of its own yet, so a light
+                    // client can't fetch that proof through `AppMsg` here;
+                    // re-exporting `JustificationStore`/`Justification` is
+                    // what lets a sync/RPC layer built outside this handler
+                    // serve it without re-deriving it from scratch.
+                    if let Some(justification) = justifications.get_justification(height) {
+                        info!(
+                            %height,
+                            justified_height = %justification.height,
+                            "Decided value unavailable, but height is covered by a retained justification"
+                        );
+                    } else if let Some(certificate) = justifications.get_pending_certificate(height) {
+                        // Not yet at a boundary (or already past one without
+                        // a retained justification), but the commit
+                        // certificate for this exact height is still in
+                        // memory - weaker than a justification since it
+                        // needs the caller to already trust the current
+                        // validator set, but still proof this height decided.
+                        info!(
+                            %height,
+                            round = %certificate.round,
+                            "Decided value unavailable, but a pending commit certificate covers this height"
+                        );
+                    }
+                }
                 let raw_value = decided_value.map(|dv| {
                     malachitebft_app_channel::app::types::sync::RawDecidedValue {
                         certificate: dv.certificate,
@@ -264,10 +490,16 @@ pub async fn run_consensus_handler(
                     valid_round
                 };
 
-                match state
-                    .get_proposal_for_restreaming(height, proposal_round, value_id)
+                // Reuse the same lookup path as `StartedRound` so a locked
+                // value and its restream always come from one source of
+                // truth, rather than a second storage query that could drift
+                // out of sync with it.
+                let restreamed = state
+                    .get_pending_proposals(height, proposal_round)
                     .await
-                {
+                    .map(|proposals| proposals.into_iter().find(|p| p.value.id() == value_id));
+
+                match restreamed {
                     Ok(Some(proposal)) => {
                         let locally_proposed =
                             malachitebft_app_channel::app::types::LocallyProposedValue {
@@ -276,8 +508,14 @@ pub async fn run_consensus_handler(
                                 value: proposal.value,
                             };
 
-                        // Stream the proposal parts
-                        for part in state.stream_proposal(locally_proposed, valid_round) {
+                        // Stream the proposal parts, erasure-coded the same
+                        // way as a fresh proposal
+                        for part in state.stream_proposal(
+                            locally_proposed,
+                            valid_round,
+                            data_shards,
+                            parity_shards,
+                        ) {
                             channels
                                 .network
                                 .send(NetworkMsg::PublishProposalPart(part))
@@ -0,0 +1,285 @@
+//! Erasure-coded proposal parts.
+//!
+//! `State::stream_proposal` splits a proposal's encoded bytes into `k` data
+//! shards and `m` parity shards using Reed-Solomon coding, instead of `k`
+//! data-only parts that all must arrive. A validator that is missing any
+//! `m` (or fewer) of the `k + m` shards - a dropped packet, a slow link -
+//! can still reconstruct the value from whichever `k` shards it did
+//! receive, without waiting on a full restream.
+//!
+//! [`encode_shards`]/[`ShardReassembly`] are the wire-format and reassembly
+//! half of the feature: they don't yet build or check into
+//! `State::stream_proposal`/`State::received_proposal_part` themselves,
+//! since that's where the actual part encoding/decoding lives.
+
+use std::collections::HashMap;
+
+use reed_solomon_erasure::galois_8::ReedSolomon;
+
+/// Identifies which proposal a shard belongs to.
+pub type ShardKey = (u64, i64, [u8; 32]);
+
+/// Metadata carried alongside a shard's bytes in every `PublishProposalPart`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ShardHeader {
+    /// Index of this shard within the `k + m` scheme (data shards first).
+    pub index: usize,
+    /// Number of data shards in the scheme.
+    pub k: usize,
+    /// Number of parity shards in the scheme.
+    pub m: usize,
+    /// Length of the original, unpadded data in bytes. Shards are all
+    /// padded up to a common `shard_len`, so this is what lets
+    /// reconstruction strip that padding back off again.
+    pub original_len: usize,
+}
+
+impl ShardHeader {
+    pub fn total_shards(&self) -> usize {
+        self.k + self.m
+    }
+}
+
+/// Accumulates shards for a single (height, round, value_id) until enough
+/// have arrived to reconstruct the original bytes.
+pub struct ShardAssemblyBuffer {
+    header: ShardHeader,
+    /// `None` until the shard at that index has arrived.
+    shards: Vec<Option<Vec<u8>>>,
+    received: usize,
+}
+
+impl ShardAssemblyBuffer {
+    pub fn new(header: ShardHeader) -> Self {
+        Self {
+            shards: vec![None; header.total_shards()],
+            header,
+            received: 0,
+        }
+    }
+
+    /// Record a shard. Returns the reconstructed value bytes once at least
+    /// `k` distinct shards have been received, `None` otherwise.
+    pub fn insert(&mut self, header: ShardHeader, shard: Vec<u8>) -> Option<Vec<u8>> {
+        // Only the scheme has to match the buffer's; `index` legitimately
+        // differs between shards of the same value.
+        if header.k != self.header.k
+            || header.m != self.header.m
+            || header.original_len != self.header.original_len
+            || header.index >= self.shards.len()
+        {
+            return None;
+        }
+
+        if self.shards[header.index].is_none() {
+            self.shards[header.index] = Some(shard);
+            self.received += 1;
+        }
+
+        if self.received < self.header.k {
+            return None;
+        }
+
+        self.reconstruct()
+    }
+
+    fn reconstruct(&self) -> Option<Vec<u8>> {
+        let shard_len = self.shards.iter().flatten().map(Vec::len).max()?;
+        let mut shards: Vec<Option<Vec<u8>>> = self
+            .shards
+            .iter()
+            .map(|shard| shard.clone().map(|mut bytes| {
+                bytes.resize(shard_len, 0);
+                bytes
+            }))
+            .collect();
+
+        let rs = ReedSolomon::new(self.header.k, self.header.m).ok()?;
+        rs.reconstruct(&mut shards).ok()?;
+
+        let mut data = Vec::with_capacity(shard_len * self.header.k);
+        for shard in shards.into_iter().take(self.header.k) {
+            data.extend(shard?);
+        }
+        // Strip the padding `encode_shards` added to round up to `shard_len`
+        // per shard, so the bytes handed to `decode_value` exactly match
+        // what a full (non-sharded) restream would have produced.
+        data.truncate(self.header.original_len);
+        Some(data)
+    }
+}
+
+/// Per-value shard buffers, keyed by (height, round, value_id) so shards for
+/// different proposals never mix.
+#[derive(Default)]
+pub struct ShardReassembly {
+    buffers: HashMap<ShardKey, ShardAssemblyBuffer>,
+}
+
+impl ShardReassembly {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Feed a newly received shard. Returns the reconstructed value bytes
+    /// once enough shards have arrived for `key`, and removes the buffer for
+    /// `key`. Completed keys are not remembered: a shard for `key` arriving
+    /// after that starts a fresh buffer, which returns the value again if it
+    /// collects `k` shards (e.g. from a restream).
+    pub fn insert(&mut self, key: ShardKey, header: ShardHeader, shard: Vec<u8>) -> Option<Vec<u8>> {
+        let buffer = self
+            .buffers
+            .entry(key)
+            .or_insert_with(|| ShardAssemblyBuffer::new(header));
+
+        let reconstructed = buffer.insert(header, shard);
+        if reconstructed.is_some() {
+            self.buffers.remove(&key);
+        }
+        reconstructed
+    }
+
+    /// Drop buffers for any height below `height`, so shards for a round
+    /// that was skipped or abandoned (never reaching `k` shards) don't pile
+    /// up for the lifetime of the process.
+    pub fn prune_below(&mut self, height: u64) {
+        self.buffers.retain(|(h, _, _), _| *h >= height);
+    }
+}
+
+/// Split `data` into `k` data shards and `m` parity shards, returning each
+/// shard tagged with the [`ShardHeader`] it should be published under.
+pub fn encode_shards(data: &[u8], k: usize, m: usize) -> Option<Vec<(ShardHeader, Vec<u8>)>> {
+    if k == 0 {
+        return None;
+    }
+    // `chunks` panics on a zero chunk size, which `div_ceil` would otherwise
+    // produce for empty `data`; a shard still needs at least one byte.
+    let shard_len = data.len().div_ceil(k).max(1);
+    let mut shards: Vec<Vec<u8>> = data
+        .chunks(shard_len)
+        .map(|chunk| {
+            let mut shard = chunk.to_vec();
+            shard.resize(shard_len, 0);
+            shard
+        })
+        .collect();
+    shards.resize(k, vec![0; shard_len]);
+    shards.extend(std::iter::repeat(vec![0; shard_len]).take(m));
+
+    let rs = ReedSolomon::new(k, m).ok()?;
+    rs.encode(&mut shards).ok()?;
+
+    let original_len = data.len();
+    Some(
+        shards
+            .into_iter()
+            .enumerate()
+            .map(|(index, shard)| {
+                (
+                    ShardHeader {
+                        index,
+                        k,
+                        m,
+                        original_len,
+                    },
+                    shard,
+                )
+            })
+            .collect(),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const KEY: ShardKey = (1, 0, [0; 32]);
+
+    #[test]
+    fn round_trips_through_the_k_data_shards() {
+        let data = b"the quick brown fox jumps over the lazy dog".to_vec();
+        let shards = encode_shards(&data, 4, 2).unwrap();
+
+        // Drop the two parity shards; the k data shards alone are enough.
+        let mut reassembly = ShardReassembly::new();
+        let mut reconstructed = None;
+        for (header, shard) in shards.into_iter().take(4) {
+            reconstructed = reassembly.insert(KEY, header, shard);
+        }
+        assert_eq!(reconstructed, Some(data));
+    }
+
+    #[test]
+    fn reconstructs_from_any_k_shards_including_parity() {
+        let data = b"some proposal bytes that do not divide evenly by four".to_vec();
+        let shards = encode_shards(&data, 4, 2).unwrap();
+
+        // Drop two of the four data shards instead, forcing reconstruction
+        // to lean on the parity shards.
+        let mut reassembly = ShardReassembly::new();
+        let mut reconstructed = None;
+        for (header, shard) in shards.into_iter().skip(2) {
+            reconstructed = reassembly.insert(KEY, header, shard);
+        }
+        assert_eq!(reconstructed, Some(data));
+    }
+
+    #[test]
+    fn strips_padding_for_lengths_not_a_multiple_of_k() {
+        // 7 bytes over k=4 forces padding up to shard_len=2 (8 bytes total);
+        // a naive reconstruct would hand back 8 bytes instead of 7.
+        let data = b"1234567".to_vec();
+        let shards = encode_shards(&data, 4, 2).unwrap();
+
+        let mut reassembly = ShardReassembly::new();
+        let mut reconstructed = None;
+        for (header, shard) in shards.into_iter().take(4) {
+            reconstructed = reassembly.insert(KEY, header, shard);
+        }
+        assert_eq!(reconstructed, Some(data));
+    }
+
+    #[test]
+    fn encode_shards_rejects_zero_data_shards() {
+        assert!(encode_shards(b"anything", 0, 2).is_none());
+    }
+
+    #[test]
+    fn encode_shards_handles_empty_data_without_panicking() {
+        let shards = encode_shards(&[], 4, 2).unwrap();
+        assert_eq!(shards.len(), 6);
+    }
+
+    #[test]
+    fn buffer_ignores_a_shard_from_a_mismatched_scheme() {
+        let data = b"abcdefgh".to_vec();
+        let mut shards = encode_shards(&data, 4, 2).unwrap();
+        let (mut mismatched_header, shard) = shards.pop().unwrap();
+        mismatched_header.k = 3;
+
+        let mut buffer = ShardAssemblyBuffer::new(shards[0].0);
+        assert_eq!(buffer.insert(mismatched_header, shard), None);
+    }
+
+    #[test]
+    fn reassembly_prune_below_drops_only_older_heights() {
+        let data = b"abcd".to_vec();
+        let shards = encode_shards(&data, 4, 2).unwrap();
+
+        let mut reassembly = ShardReassembly::new();
+        // Start, but don't finish, a buffer at height 5.
+        let (header, shard) = shards[0].clone();
+        reassembly.insert((5, 0, [0; 32]), header, shard);
+        assert_eq!(reassembly.buffers.len(), 1);
+
+        reassembly.prune_below(10);
+        assert!(reassembly.buffers.is_empty());
+
+        // A buffer at the prune height itself is kept.
+        let (header, shard) = shards[0].clone();
+        reassembly.insert((10, 0, [0; 32]), header, shard);
+        reassembly.prune_below(10);
+        assert_eq!(reassembly.buffers.len(), 1);
+    }
+}
@@ -0,0 +1,147 @@
+//! Finality justifications.
+//!
+//! A justification is the aggregated commit certificate for a decided height
+//! plus the validator set needed to verify it. Keeping one around for every
+//! decided height would grow without bound, so we only retain justifications
+//! at period boundaries (`height % justification_period == 0`) long term;
+//! certificates for the heights in between are only kept until the next
+//! boundary is justified, then pruned. A light client that trusts the
+//! validator set at boundary `N` can verify the justification at boundary
+//! `N + justification_period` without replaying any of the blocks between.
+
+use std::collections::BTreeMap;
+
+use malachitebft_core_types::{CommitCertificate, Height as _};
+
+use crate::app::State;
+use crate::context::MalachiteContext;
+use crate::types::{Height, ValidatorSet};
+
+/// A compact, independently verifiable proof that a height was finalized.
+#[derive(Debug, Clone)]
+pub struct Justification {
+    /// Height this justification attests to.
+    pub height: Height,
+    /// The aggregated commit certificate consensus produced for `height`.
+    pub certificate: CommitCertificate<MalachiteContext>,
+    /// The validator set that signed `certificate`, so the justification can
+    /// be checked without looking anything else up.
+    pub validator_set: ValidatorSet,
+}
+
+/// Keeps commit certificates in memory and derives period-boundary
+/// justifications from them, pruning everything else. Nothing is written to
+/// disk; see [`JustificationStore::rebuild_from_state`] for restarts.
+pub struct JustificationStore {
+    /// Number of heights between two retained justifications.
+    period: u64,
+    /// Certificates for heights decided since the last boundary, kept only
+    /// until that boundary is justified.
+    pending_certificates: BTreeMap<Height, CommitCertificate<MalachiteContext>>,
+    /// Justifications retained long-term, one per period boundary.
+    justifications: BTreeMap<Height, Justification>,
+}
+
+impl JustificationStore {
+    /// Create a store that justifies every `period` decided heights.
+    pub fn new(period: u64) -> Self {
+        Self {
+            period: period.max(1),
+            pending_certificates: BTreeMap::new(),
+            justifications: BTreeMap::new(),
+        }
+    }
+
+    /// Create a store and repopulate its period-boundary justifications from
+    /// `state`'s own persisted decided-value history, from
+    /// `state.get_earliest_height()` up to `state.current_height()`.
+    ///
+    /// The in-memory store is otherwise lost across a restart, and this
+    /// replay is the only recovery path: `justifications` itself has no
+    /// on-disk representation of its own. That makes this reconstruction
+    /// only as durable as `State`'s raw decided-value retention - if the raw
+    /// decided value at a boundary height has *already* been pruned by the
+    /// time a restart runs this, that boundary's justification is lost for
+    /// good, not just rebuilt more slowly. Giving `justifications` its own
+    /// persistence, independent of raw decided-value retention, is the only
+    /// way to close that gap; until then, boundary retention and
+    /// justification retention must be kept in lockstep by whoever prunes
+    /// decided values.
+    pub async fn rebuild_from_state(period: u64, state: &State) -> eyre::Result<Self> {
+        let mut store = Self::new(period);
+
+        let earliest = state.get_earliest_height().await;
+        let current = state.current_height()?;
+
+        let mut height = earliest;
+        while height <= current {
+            if store.is_boundary(height) {
+                if let Some(decided) = state.get_decided_value(height).await {
+                    store.justifications.insert(
+                        height,
+                        Justification {
+                            height,
+                            certificate: decided.certificate,
+                            validator_set: state.get_validator_set(height),
+                        },
+                    );
+                }
+            }
+            height = height.increment();
+        }
+
+        Ok(store)
+    }
+
+    /// Record a newly decided height. If `height` lands on a period
+    /// boundary, bundle it (and the validator set needed to verify it) into
+    /// a retained justification and drop the intermediate certificates that
+    /// led up to it.
+    pub fn record_decided(
+        &mut self,
+        height: Height,
+        certificate: CommitCertificate<MalachiteContext>,
+        validator_set: ValidatorSet,
+    ) {
+        if self.is_boundary(height) {
+            self.justifications.insert(
+                height,
+                Justification {
+                    height,
+                    certificate,
+                    validator_set,
+                },
+            );
+            self.pending_certificates
+                .retain(|&pending_height, _| pending_height > height);
+        } else {
+            self.pending_certificates.insert(height, certificate);
+        }
+    }
+
+    /// Look up the justification covering `height`, i.e. the justification
+    /// at the nearest period boundary at or before `height`.
+    pub fn get_justification(&self, height: Height) -> Option<&Justification> {
+        self.justifications
+            .range(..=height)
+            .next_back()
+            .map(|(_, justification)| justification)
+    }
+
+    /// Look up the raw commit certificate decided at `height`, if it hasn't
+    /// reached a period boundary (and so been folded into a retained
+    /// [`Justification`]) or been pruned past one yet.
+    ///
+    /// This is weaker than [`JustificationStore::get_justification`] - it
+    /// doesn't carry the validator set needed to verify it standalone - but
+    /// it's already in memory, so a caller that already trusts the current
+    /// validator set can use it immediately instead of waiting for the next
+    /// boundary to be justified.
+    pub fn get_pending_certificate(&self, height: Height) -> Option<&CommitCertificate<MalachiteContext>> {
+        self.pending_certificates.get(&height)
+    }
+
+    fn is_boundary(&self, height: Height) -> bool {
+        height.as_u64() % self.period == 0
+    }
+}
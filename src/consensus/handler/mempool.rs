@@ -0,0 +1,221 @@
+//! Narwhal-style batch mempool.
+//!
+//! Block data dissemination is decoupled from consensus: validators gossip
+//! transaction batches out of band and collect availability certificates for
+//! them independently of any particular round. `propose_value` then only
+//! needs to reference an ordered list of batch digests rather than ship the
+//! underlying transactions again, and a validator that already holds a
+//! referenced batch can resolve it locally instead of waiting on a restream.
+//!
+//! Batch gossip and certificate collection themselves are expected to run on
+//! their own network topic, separate from the consensus `NetworkMsg`s this
+//! handler already speaks; this module only covers the mempool's local
+//! state - what batches and certificates we currently hold.
+//!
+//! That gossip topic does not exist in this source tree (no `NetworkMsg`
+//! variant carries batches or certificates here), so a peer's batches never
+//! reach our `Mempool` today. Until that lands, [`Mempool::self_certify`]
+//! is the only thing that populates it: a node always trusts data it built
+//! itself, so the digest-based proposal path in `GetValue` only ever
+//! proposes batches *this* node produced, falling back to a full-value
+//! proposal (and self-certifying its contents) whenever the mempool holds no
+//! available batch at all. This keeps the feature additive rather
+//! than a silent replacement of the full-value path.
+//!
+//! A self-certified batch is only ever meant to back one proposal, so it
+//! must not linger in `batches`/`certificates` after the height it was
+//! proposed for decides - otherwise `select_available_digests` would keep
+//! re-selecting it at every later height forever. `GetValue` records what it
+//! proposed via [`Mempool::mark_proposed`]; `Decided` evicts it via
+//! [`Mempool::evict_decided`].
+
+use std::collections::HashMap;
+
+/// Content-address of a batch: the hash of its encoded transactions.
+pub type BatchDigest = [u8; 32];
+
+/// A batch of transactions gossiped between validators.
+#[derive(Debug, Clone)]
+pub struct Batch {
+    pub digest: BatchDigest,
+    pub transactions: Vec<Vec<u8>>,
+}
+
+/// Proof that a batch was gossiped to and acknowledged by enough validators
+/// to be considered available.
+#[derive(Debug, Clone)]
+pub struct BatchCertificate {
+    pub digest: BatchDigest,
+    /// Signatures from validators attesting they hold `digest`.
+    pub signatures: Vec<Vec<u8>>,
+}
+
+/// Local view of the batches and availability certificates this node holds.
+#[derive(Default)]
+pub struct Mempool {
+    batches: HashMap<BatchDigest, Batch>,
+    certificates: HashMap<BatchDigest, BatchCertificate>,
+    /// Digests proposed for a given (height, round) by this node, kept only
+    /// long enough to evict them once that height decides - otherwise
+    /// `select_available_digests` would keep re-selecting an already-decided
+    /// batch at every later height forever.
+    proposed: HashMap<(u64, i64), Vec<BatchDigest>>,
+}
+
+impl Mempool {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Record a batch gossiped by a peer (or built locally).
+    pub fn insert_batch(&mut self, batch: Batch) {
+        self.batches.insert(batch.digest, batch);
+    }
+
+    /// Record an availability certificate for a batch.
+    pub fn insert_certificate(&mut self, certificate: BatchCertificate) {
+        self.certificates.insert(certificate.digest, certificate);
+    }
+
+    /// Record a batch this node itself just built and immediately consider
+    /// it available, without waiting on gossip acknowledgements: a node
+    /// always trusts data it produced. Returns the batch's digest so the
+    /// caller can reference it in a proposal right away.
+    pub fn self_certify(&mut self, batch: Batch) -> BatchDigest {
+        let digest = batch.digest;
+        self.insert_certificate(BatchCertificate {
+            digest,
+            signatures: Vec::new(),
+        });
+        self.insert_batch(batch);
+        digest
+    }
+
+    /// Whether we hold the batch itself (not just a certificate for it).
+    pub fn has_batch(&self, digest: &BatchDigest) -> bool {
+        self.batches.contains_key(digest)
+    }
+
+    pub fn get_batch(&self, digest: &BatchDigest) -> Option<&Batch> {
+        self.batches.get(digest)
+    }
+
+    /// Digests with an availability certificate whose batch we also hold, in
+    /// hash-map iteration order (no particular order), capped at
+    /// `gas_target / gas_per_batch` batches but always allowing at least one.
+    pub fn select_available_digests(&self, gas_target: u64, gas_per_batch: u64) -> Vec<BatchDigest> {
+        let max_batches = (gas_target / gas_per_batch.max(1)).max(1) as usize;
+        self.certificates
+            .keys()
+            .filter(|digest| self.has_batch(digest))
+            .take(max_batches)
+            .copied()
+            .collect()
+    }
+
+    /// Record which digests this node proposed for `(height, round)`, so
+    /// [`Mempool::evict_decided`] knows what to drop once that height
+    /// decides.
+    pub fn mark_proposed(&mut self, height: u64, round: i64, digests: Vec<BatchDigest>) {
+        self.proposed.insert((height, round), digests);
+    }
+
+    /// `height` has decided: drop the batches (and their certificates) this
+    /// node proposed for it in any round, so they are never selected again, and forget
+    /// the proposed-digest bookkeeping for every earlier height too, since at
+    /// most one round per height can ever decide.
+    pub fn evict_decided(&mut self, height: u64, _round: i64) {
+        // Every round this node proposed at `height` is evicted, not only the
+        // decided one: a batch from an undecided round is just as stale.
+        let (batches, certificates) = (&mut self.batches, &mut self.certificates);
+        self.proposed.retain(|&(h, _), digests| {
+            if h == height {
+                for digest in digests.iter() {
+                    batches.remove(digest);
+                    certificates.remove(digest);
+                }
+            }
+            h > height
+        });
+    }
+
+    /// Of `digests`, which ones we do not hold the batch for and must fetch.
+    pub fn missing(&self, digests: &[BatchDigest]) -> Vec<BatchDigest> {
+        digests
+            .iter()
+            .filter(|digest| !self.has_batch(digest))
+            .copied()
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn batch(digest: BatchDigest) -> Batch {
+        Batch {
+            digest,
+            transactions: vec![vec![1, 2, 3]],
+        }
+    }
+
+    #[test]
+    fn select_available_digests_requires_both_batch_and_certificate() {
+        let mut mempool = Mempool::new();
+        mempool.insert_batch(batch([1; 32])); // batch only, no certificate
+        mempool.insert_certificate(BatchCertificate {
+            digest: [2; 32],
+            signatures: vec![],
+        }); // certificate only, no batch
+        mempool.self_certify(batch([3; 32])); // both
+
+        let selected = mempool.select_available_digests(u64::MAX, 1);
+        assert_eq!(selected, vec![[3; 32]]);
+    }
+
+    #[test]
+    fn select_available_digests_caps_at_gas_target() {
+        let mut mempool = Mempool::new();
+        for i in 0..5u8 {
+            mempool.self_certify(batch([i; 32]));
+        }
+
+        assert_eq!(mempool.select_available_digests(3, 1).len(), 3);
+        assert_eq!(mempool.select_available_digests(0, 1).len(), 1);
+    }
+
+    #[test]
+    fn evict_decided_drops_batches_proposed_for_that_height() {
+        let mut mempool = Mempool::new();
+        let digest = mempool.self_certify(batch([9; 32]));
+        mempool.mark_proposed(10, 0, vec![digest]);
+
+        mempool.evict_decided(10, 0);
+
+        assert!(!mempool.has_batch(&digest));
+        assert!(mempool.select_available_digests(u64::MAX, 1).is_empty());
+    }
+
+    #[test]
+    fn evict_decided_for_one_height_does_not_touch_another() {
+        let mut mempool = Mempool::new();
+        let kept = mempool.self_certify(batch([4; 32]));
+        mempool.mark_proposed(10, 0, vec![kept]);
+        let evicted = mempool.self_certify(batch([5; 32]));
+        mempool.mark_proposed(11, 0, vec![evicted]);
+
+        mempool.evict_decided(11, 0);
+
+        assert!(mempool.has_batch(&kept));
+        assert!(!mempool.has_batch(&evicted));
+    }
+
+    #[test]
+    fn missing_reports_only_unheld_digests() {
+        let mut mempool = Mempool::new();
+        mempool.insert_batch(batch([7; 32]));
+
+        assert_eq!(mempool.missing(&[[7; 32], [8; 32]]), vec![[8; 32]]);
+    }
+}
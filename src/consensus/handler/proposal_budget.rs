@@ -0,0 +1,31 @@
+//! Per-round proposal budget.
+//!
+//! Later rounds should produce smaller, faster-to-build blocks so that a
+//! round which is struggling to reach agreement doesn't keep proposing a
+//! payload just as expensive to assemble as round 0. We halve the gas target
+//! for every round past the first, up to [`MAX_BACKOFF_ROUNDS`], after which
+//! it stays pinned at the floor.
+//!
+//! [`gas_target_for_round`] only picks the budget; the deadline-aware build
+//! itself - `State::propose_value_with_gas_target`, `propose_value_from_digests`
+//! and `propose_empty_value`, raced against the `GetValue` timeout in
+//! `run_consensus_handler` - lives on `State` in `app.rs`, which is not part
+//! of this source tree, so those builders aren't implemented here.
+
+use malachitebft_core_types::Round;
+
+/// Gas target used for round 0 of a height.
+pub const BASE_GAS_TARGET: u64 = 30_000_000;
+
+/// Round after which the gas target stops shrinking further.
+const MAX_BACKOFF_ROUNDS: i64 = 6;
+
+/// Smallest gas target we will ever propose against, so that round-change
+/// storms still make forward progress instead of converging to zero.
+const MIN_GAS_TARGET: u64 = BASE_GAS_TARGET >> MAX_BACKOFF_ROUNDS;
+
+/// Compute `G0 / 2^min(round, k)`, floored at [`MIN_GAS_TARGET`].
+pub fn gas_target_for_round(round: Round) -> u64 {
+    let round = round.as_i64().max(0).min(MAX_BACKOFF_ROUNDS) as u32;
+    (BASE_GAS_TARGET >> round).max(MIN_GAS_TARGET)
+}
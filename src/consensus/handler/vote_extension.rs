@@ -0,0 +1,64 @@
+//! Vote extension payload for attesting to Reth execution results.
+//!
+//! Malachite lets each validator attach opaque bytes ("vote extension") to its
+//! precommit. We use that slot to carry the post-execution state the
+//! validator observed when it executed the proposed block against the Reth
+//! execution layer, so that `AppMsg::Decided` can refuse to finalize a block
+//! unless a supermajority of validators executed it to the same result.
+//!
+//! This module only defines the wire payload and its (de)serialization. The
+//! actual Reth execution hook - `State::execute_for_vote_extension`, called
+//! from the `ExtendVote`/`VerifyVoteExtension` handler arms to produce the
+//! `VoteExtension` this module encodes - lives on `State` in `app.rs`, which
+//! is not part of this source tree, so it isn't implemented here.
+
+use alloy_primitives::B256;
+
+/// The execution outcome a validator attests to for a given proposal.
+///
+/// Two validators that executed the same block should produce byte-identical
+/// extensions; any disagreement indicates a divergent execution (a buggy or
+/// malicious proposer, a non-deterministic EVM bug, etc.) and is treated as
+/// an equivocation.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct VoteExtension {
+    /// State root after applying the proposed block.
+    pub post_state_root: B256,
+    /// Root of the receipts produced while executing the proposed block.
+    pub receipts_root: B256,
+    /// Total gas used executing the proposed block.
+    pub gas_used: u64,
+}
+
+impl VoteExtension {
+    const ENCODED_LEN: usize = 32 + 32 + 8;
+
+    /// Encode this extension as the opaque bytes sent over the wire.
+    pub fn encode(&self) -> Vec<u8> {
+        let mut bytes = Vec::with_capacity(Self::ENCODED_LEN);
+        bytes.extend_from_slice(self.post_state_root.as_slice());
+        bytes.extend_from_slice(self.receipts_root.as_slice());
+        bytes.extend_from_slice(&self.gas_used.to_be_bytes());
+        bytes
+    }
+
+    /// Decode an extension previously produced by [`VoteExtension::encode`].
+    ///
+    /// Returns `None` if `bytes` is not exactly [`Self::ENCODED_LEN`] bytes
+    /// long, which is treated as a malformed extension.
+    pub fn decode(bytes: &[u8]) -> Option<Self> {
+        if bytes.len() != Self::ENCODED_LEN {
+            return None;
+        }
+
+        let post_state_root = B256::from_slice(&bytes[0..32]);
+        let receipts_root = B256::from_slice(&bytes[32..64]);
+        let gas_used = u64::from_be_bytes(bytes[64..72].try_into().ok()?);
+
+        Some(Self {
+            post_state_root,
+            receipts_root,
+            gas_used,
+        })
+    }
+}